@@ -0,0 +1,138 @@
+use crate::BrainfuckError;
+
+// A single compiled instruction together with the line/column of the first
+// source character it was compiled from, so runtime errors can still point
+// at a precise location even though several source characters may have
+// been folded into one instruction.
+#[derive(Clone, Copy)]
+pub(crate) struct Instr {
+    pub op: Op,
+    pub line: u32,
+    pub col: u32,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Op {
+    // Net delta to add to the current cell, plus the lowest and highest
+    // values the running sum touches partway through the run (both
+    // relative to the cell's value before the run started). `Checked32`
+    // needs these to detect a bound crossed mid-run even though only the
+    // net delta is actually applied.
+    Add { delta: i64, min: i64, max: i64 },
+    Move(isize),            // net delta to add to the memory pointer
+    SetZero,                // the `[-]` clear-loop idiom
+    Print,
+    Read,
+    JumpIfZero(usize),      // '[': jump to `target` (just past the matching ']') if the cell is 0
+    JumpIfNonZero(usize),   // ']': jump to `target` (just past the matching '[') if the cell is non-zero
+}
+
+// Compile a raw Brainfuck source into a compact instruction vector, once,
+// ahead of execution. Consecutive `+`/`-` collapse into a single `Add`,
+// consecutive `<`/`>` collapse into a single `Move`, and the `[-]`
+// clear-loop idiom collapses into a single `SetZero`. Loop targets are
+// resolved to absolute instruction indices up front so the interpreter
+// never has to re-scan brackets at runtime.
+pub(crate) fn compile(prog: &[char]) -> Result<Vec<Instr>, BrainfuckError> {
+    let mut ops: Vec<Instr> = Vec::new();
+    let mut loop_stack: Vec<usize> = Vec::new();
+    let mut i = 0;
+    let mut line = 1u32;
+    let mut col = 1u32;
+
+    while i < prog.len() {
+        match prog[i] {
+            '+' | '-' => {
+                let (start_line, start_col) = (line, col);
+                let mut delta: i64 = 0;
+                let mut min = 0i64;
+                let mut max = 0i64;
+
+                while i < prog.len() && (prog[i] == '+' || prog[i] == '-') {
+                    delta += if prog[i] == '+' { 1 } else { -1 };
+                    min = min.min(delta);
+                    max = max.max(delta);
+                    col += 1;
+                    i += 1;
+                }
+
+                ops.push(Instr { op: Op::Add { delta, min, max }, line: start_line, col: start_col });
+            }
+
+            '<' | '>' => {
+                let (start_line, start_col) = (line, col);
+                let mut delta: isize = 0;
+
+                while i < prog.len() && (prog[i] == '<' || prog[i] == '>') {
+                    delta += if prog[i] == '>' { 1 } else { -1 };
+                    col += 1;
+                    i += 1;
+                }
+
+                ops.push(Instr { op: Op::Move(delta), line: start_line, col: start_col });
+            }
+
+            '.' => {
+                ops.push(Instr { op: Op::Print, line, col });
+                col += 1;
+                i += 1;
+            }
+
+            ',' => {
+                ops.push(Instr { op: Op::Read, line, col });
+                col += 1;
+                i += 1;
+            }
+
+            '[' => {
+                // Only `[-]` is folded to an unconditional zero: it reaches
+                // zero by plain decrement in every cell mode. `[+]` only
+                // reaches zero by wrapping past the cell's max value, so
+                // under `Checked32` it must still run as a real loop (and
+                // error on overflow) rather than being assumed to succeed.
+                if i + 2 < prog.len() && prog[i + 1] == '-' && prog[i + 2] == ']' {
+                    ops.push(Instr { op: Op::SetZero, line, col });
+                    col += 3;
+                    i += 3;
+                } else {
+                    loop_stack.push(ops.len());
+                    ops.push(Instr { op: Op::JumpIfZero(0), line, col });
+                    col += 1;
+                    i += 1;
+                }
+            }
+
+            ']' => {
+                match loop_stack.pop() {
+                    Some(open) => {
+                        let close = ops.len();
+                        ops.push(Instr { op: Op::JumpIfNonZero(open + 1), line, col });
+                        ops[open].op = Op::JumpIfZero(close + 1);
+                        col += 1;
+                        i += 1;
+                    }
+
+                    None => return Err(BrainfuckError { message: "Unpaired ']'".to_string(), line, col })
+                }
+            }
+
+            '\n' => {
+                line += 1;
+                col = 1;
+                i += 1;
+            }
+
+            _ => {
+                col += 1;
+                i += 1;
+            }
+        }
+    }
+
+    if let Some(open) = loop_stack.pop() {
+        let Instr { line, col, .. } = ops[open];
+        return Err(BrainfuckError { message: "Loop not closed".to_string(), line, col });
+    }
+
+    Ok(ops)
+}
@@ -0,0 +1,491 @@
+mod ir;
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use ir::Op;
+
+// Controls how a single cell's arithmetic behaves on overflow/underflow.
+// The three `Wrapping*` variants model the fixed-width integer cells that
+// most real-world Brainfuck programs (and the classic `[-]` idiom) assume,
+// while `Checked32` keeps this interpreter's original behaviour of treating
+// over/underflow as a hard error.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CellMode {
+    Wrapping8,
+    Wrapping16,
+    Wrapping32,
+    Checked32,
+}
+
+impl CellMode {
+    // Parse a `--cell-mode=...` CLI flag into a CellMode, or None if the
+    // flag isn't recognized.
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        match flag.strip_prefix("--cell-mode=") {
+            Some("wrapping8") => Some(CellMode::Wrapping8),
+            Some("wrapping16") => Some(CellMode::Wrapping16),
+            Some("wrapping32") => Some(CellMode::Wrapping32),
+            Some("checked32") => Some(CellMode::Checked32),
+            _ => None
+        }
+    }
+}
+
+// Controls what `,` writes into the current cell once stdin is exhausted,
+// instead of treating end-of-input as a hard error. These three mirror the
+// conventions real-world Brainfuck programs are commonly written against.
+#[derive(Clone, Copy, PartialEq)]
+pub enum EofMode {
+    LeaveUnchanged,
+    SetZero,
+    SetMax,
+}
+
+impl EofMode {
+    // Parse a `--eof-mode=...` CLI flag into an EofMode, or None if the
+    // flag isn't recognized.
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        match flag.strip_prefix("--eof-mode=") {
+            Some("leave-unchanged") => Some(EofMode::LeaveUnchanged),
+            Some("set-zero") => Some(EofMode::SetZero),
+            Some("set-max") => Some(EofMode::SetMax),
+            _ => None
+        }
+    }
+}
+
+// Controls what happens when the memory pointer is moved past either end
+// of the tape. `Strict` keeps this interpreter's original behaviour of
+// erroring on underflow; `Wrap` instead wraps the pointer around a
+// fixed-size tape, matching the dialects that rely on that convention.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PointerMode {
+    Strict,
+    Wrap,
+}
+
+impl PointerMode {
+    // Parse a `--pointer-mode=...` CLI flag into a PointerMode, or None if
+    // the flag isn't recognized.
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        match flag.strip_prefix("--pointer-mode=") {
+            Some("strict") => Some(PointerMode::Strict),
+            Some("wrap") => Some(PointerMode::Wrap),
+            _ => None
+        }
+    }
+}
+
+// A structured interpreter error: a human-readable message plus the
+// line/column of the instruction that raised it, so callers can build
+// their own diagnostics instead of only seeing a printed line.
+#[derive(Debug)]
+pub struct BrainfuckError {
+    pub message: String,
+    pub line: u32,
+    pub col: u32,
+}
+
+impl fmt::Display for BrainfuckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}: {})", self.message, self.line, self.col)
+    }
+}
+
+impl Error for BrainfuckError {}
+
+// Number of cells held in a single allocated block of the tape.
+const TAPE_BLOCK_SIZE: usize = 4096;
+
+// Size of the tape a pointer wraps around under `PointerMode::Wrap`. This
+// only bounds the wrapping arithmetic; the tape itself still only
+// allocates the blocks a program actually touches.
+const TAPE_WRAP_SIZE: usize = 30000;
+
+// Growable, sparsely-allocated memory tape. Blocks are only boxed once a
+// cell within them is first written, so a program only pays for the memory
+// it actually touches instead of the fixed 30k array this replaced.
+struct Tape {
+    blocks: Vec<Option<Box<[u32; TAPE_BLOCK_SIZE]>>>,
+}
+
+impl Tape {
+    fn new() -> Self {
+        Self { blocks: Vec::new() }
+    }
+
+    // Read the cell at `i`, or 0 if its block has never been written to
+    fn get(&self, i: usize) -> u32 {
+        let block_idx = i / TAPE_BLOCK_SIZE;
+
+        match self.blocks.get(block_idx) {
+            Some(Some(block)) => block[i % TAPE_BLOCK_SIZE],
+            _ => 0
+        }
+    }
+
+    // Write `val` to the cell at `i`, growing the block list and lazily
+    // allocating a zeroed block as needed
+    fn write(&mut self, i: usize, val: u32) {
+        let block_idx = i / TAPE_BLOCK_SIZE;
+
+        if block_idx >= self.blocks.len() {
+            self.blocks.resize_with(block_idx + 1, || None);
+        }
+
+        let block = self.blocks[block_idx].get_or_insert_with(|| Box::new([0; TAPE_BLOCK_SIZE]));
+        block[i % TAPE_BLOCK_SIZE] = val;
+    }
+}
+
+// Main Brainfuck Struct
+pub struct Brainfuck {
+    mem: Tape,           // Memory tape, grown lazily as the program touches cells
+    mem_ptr: usize,     // Memory Pointer
+    ops: Vec<ir::Instr>, // Compiled instruction stream (see the `ir` module)
+    ip: usize,           // Instruction pointer into `ops`
+    cell_mode: CellMode, // Controls overflow/underflow behavior of '+' and '-'
+    eof_mode: EofMode,       // Controls what ',' writes once input is exhausted
+    pointer_mode: PointerMode, // Controls what '<'/'>' do at the ends of the tape
+    output: Box<dyn Write>, // Where '.' writes its output (defaults to stdout)
+    input: Box<dyn Read>,   // Where ',' reads its input from (defaults to stdin)
+}
+
+impl Brainfuck {
+    // Get a new instance of Brainfuck
+    pub fn new(prog_str: String, cell_mode: CellMode) -> Result<Self, BrainfuckError> {
+        let prog: Vec<char> = prog_str.chars().collect();
+        let ops = ir::compile(&prog)?;
+
+        let out = Self {
+            mem: Tape::new(),
+            mem_ptr: 0,
+            ops,
+            ip: 0,
+            cell_mode,
+            eof_mode: EofMode::LeaveUnchanged,
+            pointer_mode: PointerMode::Strict,
+            output: Box::new(io::stdout()),
+            input: Box::new(io::stdin()),
+        };
+
+        Ok(out)
+    }
+
+    // Redirect '.' output to the given writer instead of stdout
+    pub fn with_output(mut self, output: impl Write + 'static) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    // Redirect ',' input to the given reader instead of stdin
+    pub fn with_input(mut self, input: impl Read + 'static) -> Self {
+        self.input = Box::new(input);
+        self
+    }
+
+    // Configure what ',' writes into the current cell once input is exhausted
+    pub fn with_eof_mode(mut self, mode: EofMode) -> Self {
+        self.eof_mode = mode;
+        self
+    }
+
+    // Configure how '<'/'>' behave at the ends of the tape
+    pub fn with_pointer_mode(mut self, mode: PointerMode) -> Self {
+        self.pointer_mode = mode;
+        self
+    }
+
+    // Return the value of the cell to which the memory pointer is
+    // currently pointing to
+    fn read_cell(&self) -> u32 {
+        self.mem.get(self.mem_ptr)
+    }
+
+    // Write the supplied value to the current cell pointed to by
+    // the memory pointer
+    fn write_to_cell(&mut self, val: u32) {
+        self.mem.write(self.mem_ptr, val);
+    }
+
+    // Add `delta` to the current cell according to the configured cell mode.
+    // `min`/`max` are the lowest/highest values the run this `delta` was
+    // folded from would have passed through; `Checked32` checks those
+    // instead of just the net result, so a run that dips out of bounds and
+    // back still errors whenever the original per-character loop would. The
+    // error is reported at the first character of the run.
+    fn add_to_cell(&mut self, delta: i64, min: i64, max: i64) -> Result<(), String> {
+        match self.cell_mode {
+            CellMode::Checked32 => {
+                let cur = self.read_cell() as i64;
+
+                if cur + min < 0 {
+                    Err("Cells cannot have negative values".to_string())
+                } else if cur + max > u32::MAX as i64 {
+                    Err("Exceeded max cell value".to_string())
+                } else {
+                    self.write_to_cell((cur + delta) as u32);
+                    Ok(())
+                }
+            }
+
+            CellMode::Wrapping8 => {
+                self.write_to_cell(Self::wrap_add(self.read_cell(), delta, 8));
+                Ok(())
+            }
+
+            CellMode::Wrapping16 => {
+                self.write_to_cell(Self::wrap_add(self.read_cell(), delta, 16));
+                Ok(())
+            }
+
+            CellMode::Wrapping32 => {
+                self.write_to_cell(Self::wrap_add(self.read_cell(), delta, 32));
+                Ok(())
+            }
+        }
+    }
+
+    // Add `delta` to `cur` modulo 2^bits, wrapping past either end. `cur`
+    // is assumed to already fit within `bits` bits, which holds as long as
+    // every write to a cell goes through this same cell mode.
+    fn wrap_add(cur: u32, delta: i64, bits: u32) -> u32 {
+        let modulus = 1i64 << bits;
+        (cur as i64 + delta).rem_euclid(modulus) as u32
+    }
+
+    // The maximum value a cell can hold under the configured cell mode
+    fn cell_max(&self) -> u32 {
+        match self.cell_mode {
+            CellMode::Wrapping8 => 0xFF,
+            CellMode::Wrapping16 => 0xFFFF,
+            CellMode::Wrapping32 | CellMode::Checked32 => u32::MAX
+        }
+    }
+
+    // Read a single byte from the input, skipping '\n' the same way the
+    // original interpreter did. Once input is exhausted, fall back to the
+    // configured EofMode instead of erroring.
+    fn read_input(&mut self) -> Result<u32, String> {
+        let mut ch = [0u8];
+
+        loop {
+            match self.input.read_exact(&mut ch) {
+                Ok(_) if ch[0] == b'\n' => continue,
+                Ok(_) => return Ok(ch[0] as u32),
+
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(match self.eof_mode {
+                        EofMode::LeaveUnchanged => self.read_cell(),
+                        EofMode::SetZero => 0,
+                        EofMode::SetMax => self.cell_max()
+                    });
+                }
+
+                Err(e) => return Err(format!("Could not read input: {}", e))
+            }
+        }
+    }
+
+    // Interpret the compiled instruction stream
+    pub fn run(&mut self) -> Result<(), BrainfuckError> {
+        while self.ip < self.ops.len() {
+            let instr = self.ops[self.ip];
+
+            let status: Result<(), String> = match instr.op {
+                // Add a net delta to the current cell, per the configured cell mode
+                Op::Add { delta, min, max } => self.add_to_cell(delta, min, max).map(|_| self.ip += 1),
+
+                // Move the memory pointer by a net delta. Under
+                // `PointerMode::Strict` the tape grows lazily so moving
+                // right can never overflow, but moving left past cell 0 is
+                // still an error. Under `PointerMode::Wrap` the pointer
+                // instead wraps around a fixed-size tape.
+                Op::Move(delta) => match self.pointer_mode {
+                    PointerMode::Strict => match (self.mem_ptr as isize).checked_add(delta) {
+                        Some(new_ptr) if new_ptr >= 0 => {
+                            self.mem_ptr = new_ptr as usize;
+                            self.ip += 1;
+                            Ok(())
+                        }
+
+                        _ => Err("Tried to access memory out of range (underflow)".to_string())
+                    }
+
+                    PointerMode::Wrap => {
+                        let wrapped = (self.mem_ptr as isize + delta).rem_euclid(TAPE_WRAP_SIZE as isize);
+                        self.mem_ptr = wrapped as usize;
+                        self.ip += 1;
+                        Ok(())
+                    }
+                }
+
+                // The `[-]` clear-loop idiom: zero the current cell directly
+                Op::SetZero => {
+                    self.write_to_cell(0);
+                    self.ip += 1;
+                    Ok(())
+                }
+
+                // Converts the value of the current cell to a character and
+                // prints it. In wrapping modes the cell is masked down to
+                // its low byte first, regardless of the configured cell
+                // width. The character is written UTF-8 encoded, so values
+                // above 0x7F produce more than one output byte.
+                Op::Print => {
+                    let val = match self.cell_mode {
+                        CellMode::Checked32 => self.read_cell(),
+                        _ => self.read_cell() & 0xFF
+                    };
+
+                    match std::char::from_u32(val) {
+                        Some(ch) => write!(self.output, "{}", ch)
+                            .map_err(|e| format!("Could not write output: {}", e))
+                            .map(|_| self.ip += 1),
+                        None => Err("Could not print character".to_string())
+                    }
+                }
+
+                // Takes in a single character as input and stores its value
+                // in the current cell
+                Op::Read => self.read_input().map(|val| {
+                    self.write_to_cell(val);
+                    self.ip += 1;
+                }),
+
+                // If the value of the cell at the memory pointer is 0,
+                // jump past the corresponding ']'
+                Op::JumpIfZero(target) => {
+                    self.ip = if self.read_cell() == 0 { target } else { self.ip + 1 };
+                    Ok(())
+                }
+
+                // If the value of the cell at the memory pointer is
+                // non-zero, jump back to just after the matching '['
+                Op::JumpIfNonZero(target) => {
+                    self.ip = if self.read_cell() != 0 { target } else { self.ip + 1 };
+                    Ok(())
+                }
+            };
+
+            if let Err(message) = status {
+                return Err(BrainfuckError { message, line: instr.line, col: instr.col });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // A `Write` sink that stays reachable after being moved into a
+    // `Brainfuck`, so tests can inspect what was printed.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn contents(&self) -> Vec<u8> {
+            self.0.borrow().clone()
+        }
+    }
+
+    #[test]
+    fn with_output_captures_printed_characters() {
+        let buf = SharedBuffer::default();
+        let mut bf = Brainfuck::new("++++++++[>++++++++<-]>+.".to_string(), CellMode::Wrapping8)
+            .unwrap()
+            .with_output(buf.clone());
+
+        bf.run().unwrap();
+
+        assert_eq!(buf.contents(), b"A");
+    }
+
+    #[test]
+    fn with_input_feeds_scripted_data() {
+        let buf = SharedBuffer::default();
+        let mut bf = Brainfuck::new(",.".to_string(), CellMode::Wrapping8)
+            .unwrap()
+            .with_input(io::Cursor::new(b"Z".to_vec()))
+            .with_output(buf.clone());
+
+        bf.run().unwrap();
+
+        assert_eq!(buf.contents(), b"Z");
+    }
+
+    #[test]
+    fn new_reports_unmatched_bracket_as_a_structured_error() {
+        let err = match Brainfuck::new("[".to_string(), CellMode::Checked32) {
+            Ok(_) => panic!("expected an unmatched bracket error"),
+            Err(e) => e
+        };
+
+        assert_eq!(err.message, "Loop not closed");
+        assert_eq!((err.line, err.col), (1, 1));
+    }
+
+    #[test]
+    fn checked32_errors_on_underflow_instead_of_panicking() {
+        let mut bf = Brainfuck::new("-".to_string(), CellMode::Checked32).unwrap();
+
+        let err = bf.run().unwrap_err();
+        assert_eq!(err.message, "Cells cannot have negative values");
+    }
+
+    #[test]
+    fn wrapping8_wraps_past_zero_instead_of_erroring() {
+        let buf = SharedBuffer::default();
+        let mut bf = Brainfuck::new("-.".to_string(), CellMode::Wrapping8)
+            .unwrap()
+            .with_output(buf.clone());
+
+        bf.run().unwrap();
+
+        // '.' prints via `char::from_u32`, so the wrapped byte 0xFF comes
+        // out UTF-8 encoded as U+00FF rather than as a raw byte.
+        assert_eq!(buf.contents(), "\u{FF}".as_bytes());
+    }
+
+    #[test]
+    fn eof_mode_set_zero_fills_in_a_nul_byte_past_end_of_input() {
+        let buf = SharedBuffer::default();
+        let mut bf = Brainfuck::new(",.".to_string(), CellMode::Wrapping8)
+            .unwrap()
+            .with_input(io::Cursor::new(Vec::new()))
+            .with_eof_mode(EofMode::SetZero)
+            .with_output(buf.clone());
+
+        bf.run().unwrap();
+
+        assert_eq!(buf.contents(), vec![0]);
+    }
+
+    #[test]
+    fn pointer_mode_wrap_moves_left_from_cell_zero_instead_of_erroring() {
+        let mut bf = Brainfuck::new("<".to_string(), CellMode::Checked32)
+            .unwrap()
+            .with_pointer_mode(PointerMode::Wrap);
+
+        assert!(bf.run().is_ok());
+        assert_eq!(bf.mem_ptr, TAPE_WRAP_SIZE - 1);
+    }
+}